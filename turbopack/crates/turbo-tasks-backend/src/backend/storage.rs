@@ -1,7 +1,12 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     hash::{BuildHasherDefault, Hash},
     mem::take,
     ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
     thread::available_parallelism,
 };
 
@@ -23,26 +28,56 @@ type IndexedMap<T> = AutoMap<
     AutoMap<<T as KeyValuePair>::Key, <T as KeyValuePair>::Value>,
 >;
 
+/// A value whose presence in [`InnerStorage`] contributes to a running aggregate kept per index,
+/// so callers can ask questions like "is this task dirty?" or "what's the total dependent count?"
+/// in O(1) instead of calling [`InnerStorage::iter`] and counting.
+///
+/// The aggregate itself is always a signed count: counter-style items (e.g. reference counts
+/// updated via `update_count!`) contribute their own value, while a boolean "any present" flag
+/// (e.g. the `Dirty` key) contributes `1` while present and `0` otherwise, so "is anything under
+/// this index set" becomes `aggregate(index) > 0`.
+pub trait Aggregated {
+    /// This value's contribution to its index's running aggregate. Values that should not affect
+    /// any aggregate (the common case) can rely on the default of `0`.
+    fn aggregate_contribution(&self) -> i32 {
+        0
+    }
+}
+
+type AggregateMap<T> = AutoMap<<<T as KeyValuePair>::Key as Indexed>::Index, i32>;
+
 pub enum InnerStorage<T: KeyValuePair>
 where
     T::Key: Indexed,
 {
-    Plain { map: AutoMap<T::Key, T::Value> },
-    Indexed { map: IndexedMap<T> },
+    Plain {
+        map: AutoMap<T::Key, T::Value>,
+        aggregates: AggregateMap<T>,
+    },
+    Indexed {
+        map: IndexedMap<T>,
+        aggregates: AggregateMap<T>,
+    },
 }
 
 impl<T: KeyValuePair> InnerStorage<T>
 where
     T::Key: Indexed,
+    T::Value: Aggregated,
 {
     fn new() -> Self {
         Self::Plain {
             map: AutoMap::new(),
+            aggregates: AutoMap::new(),
         }
     }
 
     fn check_threshold(&mut self) {
-        let InnerStorage::Plain { map: plain_map } = self else {
+        let InnerStorage::Plain {
+            map: plain_map,
+            aggregates,
+        } = self
+        else {
             return;
         };
         if plain_map.len() >= INDEX_THRESHOLD {
@@ -51,7 +86,10 @@ where
                 let index = key.index();
                 map.entry(index).or_default().insert(key, value);
             }
-            *self = InnerStorage::Indexed { map };
+            *self = InnerStorage::Indexed {
+                map,
+                aggregates: take(aggregates),
+            };
         }
     }
 
@@ -79,22 +117,76 @@ where
 
     pub fn add(&mut self, item: T) -> bool {
         let (key, value) = item.into_key_and_value();
-        match self.get_map_mut(&key).entry(key) {
+        let index = key.index();
+        let contribution = value.aggregate_contribution();
+        let inserted = match self.get_map_mut(&key).entry(key) {
             Entry::Occupied(_) => false,
             Entry::Vacant(e) => {
                 e.insert(value);
                 true
             }
+        };
+        if inserted {
+            self.adjust_aggregate(index, contribution);
         }
+        inserted
     }
 
     pub fn insert(&mut self, item: T) -> Option<T::Value> {
         let (key, value) = item.into_key_and_value();
-        self.get_map_mut(&key).insert(key, value)
+        let index = key.index();
+        let new_contribution = value.aggregate_contribution();
+        let old = self.get_map_mut(&key).insert(key, value);
+        let old_contribution = old
+            .as_ref()
+            .map(T::Value::aggregate_contribution)
+            .unwrap_or(0);
+        self.adjust_aggregate(index, new_contribution - old_contribution);
+        old
     }
 
     pub fn remove(&mut self, key: &T::Key) -> Option<T::Value> {
-        self.get_map_mut(key).remove(key)
+        let index = key.index();
+        let old = self.get_map_mut(key).remove(key);
+        if let Some(old) = &old {
+            self.adjust_aggregate(index, -old.aggregate_contribution());
+        }
+        old
+    }
+
+    /// Folds `delta` into the running aggregate kept for `index`, dropping the entry once it
+    /// returns to zero so empty/never-touched indices don't leave stale zeroes behind.
+    fn adjust_aggregate(&mut self, index: <T::Key as Indexed>::Index, delta: i32) {
+        if delta == 0 {
+            return;
+        }
+        let aggregates = match self {
+            InnerStorage::Plain { aggregates, .. } => aggregates,
+            InnerStorage::Indexed { aggregates, .. } => aggregates,
+        };
+        match aggregates.entry(index) {
+            Entry::Occupied(mut e) => {
+                let new = *e.get() + delta;
+                if new == 0 {
+                    e.remove();
+                } else {
+                    *e.get_mut() = new;
+                }
+            }
+            Entry::Vacant(e) => {
+                e.insert(delta);
+            }
+        }
+    }
+
+    /// Returns the running aggregate for `index`, e.g. a total reference count or whether any
+    /// dirty flag is set, without iterating the items stored under that index.
+    pub fn aggregate(&self, index: <T::Key as Indexed>::Index) -> i32 {
+        let aggregates = match self {
+            InnerStorage::Plain { aggregates, .. } => aggregates,
+            InnerStorage::Indexed { aggregates, .. } => aggregates,
+        };
+        aggregates.get(&index).copied().unwrap_or(0)
     }
 
     pub fn get(&self, key: &T::Key) -> Option<&T::Value> {
@@ -129,12 +221,23 @@ where
             }
         }
     }
+
+    /// Consumes the storage, yielding every key/value pair it held. Used by [`Storage`]'s spill
+    /// tier to hand a cold entry's items to a [`Backing`] before evicting it.
+    pub fn into_iter_all(self) -> impl Iterator<Item = (T::Key, T::Value)> {
+        match self {
+            InnerStorage::Plain { map, .. } => Either::Left(map.into_iter()),
+            InnerStorage::Indexed { map, .. } => {
+                Either::Right(map.into_iter().flat_map(|(_, m)| m.into_iter()))
+            }
+        }
+    }
 }
 
 impl<T: KeyValuePair> InnerStorage<T>
 where
     T::Key: Indexed,
-    T::Value: Default,
+    T::Value: Default + Aggregated,
     T::Key: Clone,
 {
     pub fn update(
@@ -142,17 +245,24 @@ where
         key: &T::Key,
         update: impl FnOnce(Option<T::Value>) -> Option<T::Value>,
     ) {
+        let index = key.index();
         let map = self.get_map_mut(key);
+        let mut delta = 0;
         if let Some(value) = map.get_mut(key) {
             let v = take(value);
+            let old_contribution = v.aggregate_contribution();
             if let Some(v) = update(Some(v)) {
+                delta = v.aggregate_contribution() - old_contribution;
                 *value = v;
             } else {
+                delta = -old_contribution;
                 map.remove(key);
             }
         } else if let Some(v) = update(None) {
+            delta = v.aggregate_contribution();
             map.insert(key.clone(), v);
         }
+        self.adjust_aggregate(index, delta);
     }
 }
 
@@ -173,20 +283,136 @@ where
     }
 }
 
-pub struct Storage<K, T: KeyValuePair>
+/// A value that knows whether it's worth round-tripping through a [`Backing`] when its task's
+/// storage is spilled.
+///
+/// Transient bookkeeping (e.g. in-progress markers) should return `false` so it's simply dropped
+/// rather than persisted when its task becomes cold; the default covers the common case where
+/// every stored value is persistable.
+pub trait Persistable {
+    fn is_persistable(&self) -> bool {
+        true
+    }
+}
+
+/// Where [`Storage`] writes the `CachedDataItem`s of a cold, evicted `InnerStorage` to, and reads
+/// them back from on the next access.
+///
+/// The default, [`NoBacking`], discards whatever it is asked to save and loads nothing back;
+/// [`Storage::new`] pairs it with an unlimited resident budget, so nothing is ever evicted, which
+/// is the right behavior for a purely in-memory backend. A real persistent-cache backend plugs in
+/// an on-disk implementation.
+pub trait Backing<K, T: KeyValuePair>: Send + Sync
+where
+    T::Key: Indexed,
+{
+    fn save(&self, key: &K, items: Vec<T>);
+    fn load(&self, key: &K) -> Vec<T>;
+}
+
+pub struct NoBacking;
+
+impl<K, T: KeyValuePair> Backing<K, T> for NoBacking
+where
+    T::Key: Indexed,
+{
+    fn save(&self, _key: &K, _items: Vec<T>) {}
+
+    fn load(&self, _key: &K) -> Vec<T> {
+        Vec::new()
+    }
+}
+
+/// A slot in [`Storage`]'s map: either a resident `InnerStorage`, or a tombstone left behind by an
+/// eviction, to be transparently rehydrated from the [`Backing`] on the next access.
+enum Slot<T: KeyValuePair>
+where
+    T::Key: Indexed,
+{
+    Resident(InnerStorage<T>),
+    Spilled,
+}
+
+#[derive(Default)]
+pub struct StorageMetrics {
+    resident: AtomicUsize,
+    evicted: AtomicUsize,
+}
+
+/// An order-preserving recency tracker used as a coarse, global approximation of per-shard LRU.
+///
+/// `touch` is idempotent per key: re-touching a key moves it to the back instead of appending a
+/// second entry, so the tracker never grows past the number of distinct keys that have ever been
+/// accessed, and `pop_oldest` never returns a stale duplicate for a key that was touched again
+/// since it was queued.
+struct Recency<K> {
+    generation_of: HashMap<K, u64, BuildHasherDefault<FxHasher>>,
+    order: BTreeMap<u64, K>,
+    next_generation: u64,
+}
+
+impl<K> Default for Recency<K> {
+    fn default() -> Self {
+        Self {
+            generation_of: HashMap::default(),
+            order: BTreeMap::new(),
+            next_generation: 0,
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> Recency<K> {
+    fn touch(&mut self, key: &K) {
+        if let Some(old_generation) = self.generation_of.get(key) {
+            self.order.remove(old_generation);
+        }
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        self.order.insert(generation, key.clone());
+        self.generation_of.insert(key.clone(), generation);
+    }
+
+    fn pop_oldest(&mut self) -> Option<K> {
+        let generation = *self.order.keys().next()?;
+        let key = self.order.remove(&generation).unwrap();
+        self.generation_of.remove(&key);
+        Some(key)
+    }
+}
+
+pub struct Storage<K, T: KeyValuePair, B: Backing<K, T> = NoBacking>
 where
     T::Key: Indexed,
 {
-    map: DashMap<K, InnerStorage<T>, BuildHasherDefault<FxHasher>>,
+    map: DashMap<K, Slot<T>, BuildHasherDefault<FxHasher>>,
+    backing: B,
+    resident_budget: usize,
+    recency: Mutex<Recency<K>>,
+    metrics: StorageMetrics,
 }
 
-impl<K, T> Storage<K, T>
+impl<K, T> Storage<K, T, NoBacking>
 where
     T: KeyValuePair,
     T::Key: Indexed,
+    T::Value: Aggregated + Persistable,
     K: Eq + std::hash::Hash + Clone,
 {
     pub fn new() -> Self {
+        // `NoBacking` has nowhere to spill to, so keep every entry resident.
+        Self::with_backing(NoBacking, usize::MAX)
+    }
+}
+
+impl<K, T, B> Storage<K, T, B>
+where
+    T: KeyValuePair,
+    T::Key: Indexed,
+    T::Value: Aggregated + Persistable,
+    K: Eq + std::hash::Hash + Clone,
+    B: Backing<K, T>,
+{
+    pub fn with_backing(backing: B, resident_budget: usize) -> Self {
         let shard_amount =
             (available_parallelism().map_or(4, |v| v.get()) * 64).next_power_of_two();
         Self {
@@ -195,14 +421,35 @@ where
                 Default::default(),
                 shard_amount,
             ),
+            backing,
+            resident_budget,
+            recency: Mutex::new(Recency::default()),
+            metrics: StorageMetrics::default(),
         }
     }
 
+    /// Returns `(resident, evicted)` entry counts so a backend can tune its resident budget.
+    pub fn metrics(&self) -> (usize, usize) {
+        (
+            self.metrics.resident.load(Ordering::Relaxed),
+            self.metrics.evicted.load(Ordering::Relaxed),
+        )
+    }
+
     pub fn access_mut(&self, key: K) -> StorageWriteGuard<'_, K, T> {
-        let inner = match self.map.entry(key) {
+        // Run eviction before taking our own lock, never after: once we lock `key`'s shard below,
+        // we rehydrate it (if needed) and hand out the guard without ever releasing that lock, so
+        // nothing else can spill it out from under the caller.
+        self.touch(&key);
+        self.evict_cold_entries();
+        let mut inner = match self.map.entry(key.clone()) {
             dashmap::mapref::entry::Entry::Occupied(e) => e.into_ref(),
-            dashmap::mapref::entry::Entry::Vacant(e) => e.insert(InnerStorage::new()),
+            dashmap::mapref::entry::Entry::Vacant(e) => {
+                self.metrics.resident.fetch_add(1, Ordering::Relaxed);
+                e.insert(Slot::Resident(InnerStorage::new()))
+            }
         };
+        self.rehydrate(&key, &mut inner);
         StorageWriteGuard {
             inner: inner.into(),
         }
@@ -213,12 +460,87 @@ where
         key1: K,
         key2: K,
     ) -> (StorageWriteGuard<'_, K, T>, StorageWriteGuard<'_, K, T>) {
-        let (a, b) = get_multiple_mut(&self.map, key1, key2, || InnerStorage::new());
+        self.touch(&key1);
+        self.touch(&key2);
+        self.evict_cold_entries();
+        let (mut a, mut b) = get_multiple_mut(&self.map, key1.clone(), key2.clone(), || {
+            self.metrics.resident.fetch_add(1, Ordering::Relaxed);
+            Slot::Resident(InnerStorage::new())
+        });
+        // `a`/`b` are still held locked from `get_multiple_mut` above, so rehydrating them here,
+        // before ever releasing those locks, is atomic with handing out the resulting guards.
+        self.rehydrate(&key1, &mut a);
+        self.rehydrate(&key2, &mut b);
         (
             StorageWriteGuard { inner: a },
             StorageWriteGuard { inner: b },
         )
     }
+
+    fn touch(&self, key: &K) {
+        self.recency.lock().unwrap().touch(key);
+    }
+
+    fn rehydrate(&self, key: &K, slot: &mut Slot<T>) {
+        if matches!(slot, Slot::Spilled) {
+            let mut storage = InnerStorage::new();
+            for item in self.backing.load(key) {
+                storage.insert(item);
+            }
+            *slot = Slot::Resident(storage);
+            self.metrics.resident.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn evict_cold_entries(&self) {
+        if self.metrics.resident.load(Ordering::Relaxed) <= self.resident_budget {
+            return;
+        }
+        // Only the key selection (`pop_oldest`) needs `recency` held: it's an in-memory
+        // `BTreeMap`/`HashMap` pop, not I/O. Collect the keys to spill under the lock, then drop
+        // it before touching `self.map` or calling `backing.save()`, so a slow `Backing` (e.g.
+        // disk-bound) never serializes every other task's `access_mut`/`access_pair_mut` behind
+        // this one eviction pass.
+        let to_evict = self
+            .metrics
+            .resident
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.resident_budget);
+        let keys_to_evict = {
+            let mut recency = self.recency.lock().unwrap();
+            let mut keys = Vec::with_capacity(to_evict);
+            for _ in 0..to_evict {
+                let Some(key) = recency.pop_oldest() else {
+                    break;
+                };
+                keys.push(key);
+            }
+            keys
+        };
+        for key in keys_to_evict {
+            let Some(mut slot) = self.map.get_mut(&key) else {
+                continue;
+            };
+            let Slot::Resident(_) = &*slot else {
+                continue;
+            };
+            let Slot::Resident(storage) = std::mem::replace(&mut *slot, Slot::Spilled) else {
+                unreachable!()
+            };
+            let items: Vec<T> = storage
+                .into_iter_all()
+                .filter(|(_, value)| value.is_persistable())
+                .map(|(key, value)| T::from_key_and_value(key, value))
+                .collect();
+            // Hold the shard lock until `backing.save()` has finished: an accessor that saw the
+            // `Spilled` tombstone any earlier would rehydrate from a `backing` that doesn't have
+            // these items yet.
+            self.backing.save(&key, items);
+            drop(slot);
+            self.metrics.resident.fetch_sub(1, Ordering::Relaxed);
+            self.metrics.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 }
 
 pub struct StorageWriteGuard<'a, K, T>
@@ -226,7 +548,7 @@ where
     T: KeyValuePair,
     T::Key: Indexed,
 {
-    inner: RefMut<'a, K, InnerStorage<T>, BuildHasherDefault<FxHasher>>,
+    inner: RefMut<'a, K, Slot<T>, BuildHasherDefault<FxHasher>>,
 }
 
 impl<K, T> Deref for StorageWriteGuard<'_, K, T>
@@ -238,7 +560,10 @@ where
     type Target = InnerStorage<T>;
 
     fn deref(&self) -> &Self::Target {
-        &self.inner
+        match &*self.inner {
+            Slot::Resident(storage) => storage,
+            Slot::Spilled => unreachable!("StorageWriteGuard is only handed out after rehydration"),
+        }
     }
 }
 
@@ -249,7 +574,10 @@ where
     K: Eq + Hash,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
+        match &mut *self.inner {
+            Slot::Resident(storage) => storage,
+            Slot::Spilled => unreachable!("StorageWriteGuard is only handed out after rehydration"),
+        }
     }
 }
 
@@ -326,6 +654,9 @@ macro_rules! update {
     };
 }
 
+// Still detects zero-crossings per key, same as before `Aggregated` existed. Where a value's
+// `aggregate_contribution` is its count, `InnerStorage::aggregate` holds the sum of the counts
+// under its index; counts can be negative, so a zero sum does not mean every count is zero.
 macro_rules! update_count {
     ($task:ident, $key:ident $input:tt, $update:expr) => {
         match $update {
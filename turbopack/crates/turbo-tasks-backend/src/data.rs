@@ -0,0 +1,90 @@
+use turbo_tasks::{event::Event, CellId, KeyValuePair, SharedReference, TaskId};
+
+use crate::backend::{
+    indexed::Indexed,
+    storage::{Aggregated, Persistable},
+};
+
+/// Tracks a cell whose task is currently (re-)computing it; removed once the recompute finishes
+/// and [`UpdateCellOperation`][crate::backend::operation::UpdateCellOperation] replaces it with a
+/// fresh [`CachedDataItem::CellData`].
+#[derive(Debug)]
+pub struct InProgressCellState {
+    /// Notified (with `usize::MAX`, i.e. "wake everyone") once the recompute finishes, so readers
+    /// blocked on the old value can retry against the new one.
+    pub event: Event,
+}
+
+/// The persistent data kept per task, one variant per kind of fact the backend tracks about it.
+///
+/// `#[derive(KeyValuePair)]` splits each variant into a key (every field but `value`) and a value
+/// (just the `value` field), producing the paired [`CachedDataItemKey`]/[`CachedDataItemValue`]
+/// enums that [`InnerStorage`][crate::backend::storage::InnerStorage] actually stores.
+#[derive(Debug, Clone, KeyValuePair)]
+pub enum CachedDataItem {
+    /// The current value stored in a cell.
+    CellData { cell: CellId, value: SharedReference },
+    /// Marks `cell` as being (re-)computed right now.
+    InProgressCell {
+        cell: CellId,
+        value: InProgressCellState,
+    },
+    /// Set whenever this task needs to be (re-)executed; cleared once it finishes.
+    Dirty { value: () },
+    /// Records that `task` read `cell`, so writing `cell` must invalidate `task`.
+    CellDependent { cell: CellId, task: TaskId, value: () },
+}
+
+/// Per-variant index values for [`CachedDataItemKey`], used by `iter_many!`/`get_many!` to scan
+/// only the items of one kind instead of every item a task has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CachedDataItemIndex {
+    CellData,
+    InProgressCell,
+    Dirty,
+    CellDependent,
+}
+
+pub mod indicies {
+    pub use super::CachedDataItemIndex::*;
+}
+
+impl Indexed for CachedDataItemKey {
+    type Index = CachedDataItemIndex;
+
+    fn index(&self) -> Self::Index {
+        match self {
+            CachedDataItemKey::CellData { .. } => CachedDataItemIndex::CellData,
+            CachedDataItemKey::InProgressCell { .. } => CachedDataItemIndex::InProgressCell,
+            CachedDataItemKey::Dirty {} => CachedDataItemIndex::Dirty,
+            CachedDataItemKey::CellDependent { .. } => CachedDataItemIndex::CellDependent,
+        }
+    }
+}
+
+impl Aggregated for CachedDataItemValue {
+    fn aggregate_contribution(&self) -> i32 {
+        match self {
+            // `Dirty`'s mere presence is the signal callers care about, so its contribution is a
+            // flat `1`; `InnerStorage::aggregate(indicies::Dirty) > 0` then answers "is this task
+            // dirty?" in O(1) instead of an `iter`+`count`.
+            CachedDataItemValue::Dirty { .. } => 1,
+            CachedDataItemValue::CellData { .. }
+            | CachedDataItemValue::InProgressCell { .. }
+            | CachedDataItemValue::CellDependent { .. } => 0,
+        }
+    }
+}
+
+impl Persistable for CachedDataItemValue {
+    fn is_persistable(&self) -> bool {
+        match self {
+            // An in-progress marker describes a recompute that died with the process that started
+            // it; there's nothing meaningful to round-trip through a `Backing` once reloaded.
+            CachedDataItemValue::InProgressCell { .. } => false,
+            CachedDataItemValue::CellData { .. }
+            | CachedDataItemValue::Dirty { .. }
+            | CachedDataItemValue::CellDependent { .. } => true,
+        }
+    }
+}
@@ -51,6 +51,7 @@ mod id_factory;
 mod invalidation;
 mod join_iter_ext;
 mod key_value_pair;
+mod local_cell;
 #[doc(hidden)]
 pub mod macro_helpers;
 mod magic_any;
@@ -94,6 +95,7 @@ pub use invalidation::{
 };
 pub use join_iter_ext::{JoinIterExt, TryFlatJoinIterExt, TryJoinIterExt};
 pub use key_value_pair::KeyValuePair;
+pub use local_cell::{local_cell, with_local_cell_scope, LocalCellId};
 pub use magic_any::MagicAny;
 pub use manager::{
     dynamic_call, dynamic_this_call, emit, mark_dirty_when_persisted, mark_finished, mark_stateful,
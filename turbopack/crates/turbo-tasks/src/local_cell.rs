@@ -0,0 +1,153 @@
+//! Task-local ("ephemeral") cells.
+//!
+//! A normal [`Vc`] cell lives in the backend's persistent [`Storage`][crate::backend], is keyed by
+//! `(TaskId, CellId)`, can be serialized for the persistent cache, and reading it registers a
+//! `CellDependent` edge so the backend knows to invalidate dependents. That's the right trade-off
+//! for values that outlive a single task execution, but it's overkill for throwaway intermediate
+//! results that a task only needs for the duration of its own run.
+//!
+//! A local cell skips all of that: its value lives in a small per-task arena that is dropped
+//! wholesale when the task (and any local tasks it spawned) finishes. It is never written through
+//! the backend, never serialized, and never tracked for dependencies. If a local [`Vc`] needs to
+//! escape its originating task (e.g. it's returned from the task, or stored into a persistent
+//! cell), it is "resolved" by copying its value into a real persistent cell on demand.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    hash::BuildHasherDefault,
+    sync::{Mutex, OnceLock},
+};
+
+use rustc_hash::FxHasher;
+
+use crate::{manager::turbo_tasks, raw_vc::RawVc, TaskId, Vc, VcValueType};
+
+/// Identifies a single local cell within the arena of the task that created it.
+///
+/// Unlike [`CellId`][crate::CellId], this index is never persisted and is only meaningful for the
+/// lifetime of the owning task's execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalCellId(u32);
+
+/// The arena of local cells for a single in-progress task execution.
+///
+/// Values are stored type-erased as `Box<dyn Any + Send + Sync>`, since local cells never need to
+/// be serialized.
+#[derive(Default)]
+struct LocalCellArena {
+    cells: Vec<Box<dyn std::any::Any + Send + Sync>>,
+}
+
+impl LocalCellArena {
+    fn push<T: VcValueType>(&mut self, value: T) -> LocalCellId {
+        let index = self.cells.len();
+        self.cells.push(Box::new(value));
+        LocalCellId(index as u32)
+    }
+
+    fn get<T: VcValueType + Clone>(&self, id: LocalCellId) -> T {
+        self.cells[id.0 as usize]
+            .downcast_ref::<T>()
+            .expect("local cell type mismatch")
+            .clone()
+    }
+}
+
+/// All local cell arenas currently in use, keyed by the [`TaskId`] that owns them.
+///
+/// Entries are created lazily on first use by a task and removed wholesale once the task (and any
+/// local tasks spawned from it) finishes executing, at which point the arena and everything in it
+/// is simply dropped rather than flushed anywhere.
+static LOCAL_CELL_ARENAS: OnceLock<Mutex<HashMap<TaskId, LocalCellArena, BuildHasherDefault<FxHasher>>>> =
+    OnceLock::new();
+
+fn arenas() -> &'static Mutex<HashMap<TaskId, LocalCellArena, BuildHasherDefault<FxHasher>>> {
+    LOCAL_CELL_ARENAS.get_or_init(Default::default)
+}
+
+thread_local! {
+    /// The task currently executing on this thread, if any. Local cells are always created and
+    /// read relative to this task, never relative to the task that's merely *reading* a [`Vc`].
+    static CURRENT_LOCAL_TASK: RefCell<Option<TaskId>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous `CURRENT_LOCAL_TASK` and drops `task_id`'s arena on drop, whether
+/// [`with_local_cell_scope`]'s closure returned normally or unwound.
+///
+/// Without this, a panicking task would leave `CURRENT_LOCAL_TASK` pointing at a dead task (and
+/// its arena leaked) for the rest of the worker thread's life, corrupting local cell scoping for
+/// whatever runs on the thread next.
+struct LocalCellScopeGuard {
+    task_id: TaskId,
+    previous: Option<TaskId>,
+}
+
+impl Drop for LocalCellScopeGuard {
+    fn drop(&mut self) {
+        CURRENT_LOCAL_TASK.with(|cell| *cell.borrow_mut() = self.previous);
+        arenas().lock().unwrap().remove(&self.task_id);
+    }
+}
+
+/// Marks `task_id` as the currently-executing task on this thread for the duration of `f`, and
+/// drops its local cell arena (if any) once `f` returns or panics.
+///
+/// This must be called by the task-execution loop around every invocation of a task's native
+/// function (nested for local tasks spawned from within it, so that a child's local cells are
+/// cleaned up before the parent's); without that wiring, [`local_cell`] has no task to attribute
+/// its arena to and panics via [`current_local_task`].
+pub fn with_local_cell_scope<R>(task_id: TaskId, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT_LOCAL_TASK.with(|cell| cell.replace(Some(task_id)));
+    let _guard = LocalCellScopeGuard { task_id, previous };
+    f()
+}
+
+fn current_local_task() -> TaskId {
+    CURRENT_LOCAL_TASK
+        .with(|cell| *cell.borrow())
+        .expect("local_cell() can only be called from within a running task")
+}
+
+impl<T: VcValueType> Vc<T> {
+    /// Constructs a `Vc` pointing at a local cell rather than a persistent one. This is the
+    /// implementation behind [`local_cell`]; call that instead of this directly.
+    pub(crate) fn local_cell_private(task_id: TaskId, id: LocalCellId) -> Self {
+        Vc::from(RawVc::LocalCell(task_id, id))
+    }
+}
+
+/// Places `value` into the current task's local arena and returns a [`Vc`] pointing at it.
+///
+/// The returned `Vc` is cheap to create and read within the task (and any local tasks it spawns),
+/// but it is not valid to store outside of that scope. Call [`Vc::to_resolved`] (which calls
+/// through to [`resolve_local_cell`]) before letting a local `Vc` escape, e.g. by returning it from
+/// the task or writing it into a persistent cell.
+pub fn local_cell<T: VcValueType>(value: T) -> Vc<T> {
+    let task_id = current_local_task();
+    let id = arenas()
+        .lock()
+        .unwrap()
+        .entry(task_id)
+        .or_default()
+        .push(value);
+    Vc::local_cell_private(task_id, id)
+}
+
+/// Promotes the value behind a local cell into a real, persistent cell so it can safely outlive
+/// the task that created it.
+///
+/// This runs the normal [`UpdateCellOperation`][crate::backend::operation::UpdateCellOperation]
+/// path (via the backend API), exactly as if the task had called `.cell()` directly, and returns a
+/// [`RawVc`] pointing at the freshly written persistent cell.
+pub fn resolve_local_cell<T: VcValueType + Clone>(task_id: TaskId, id: LocalCellId) -> RawVc {
+    let value = arenas()
+        .lock()
+        .unwrap()
+        .get(&task_id)
+        .expect("local cell arena missing for its owning task")
+        .get::<T>(id);
+    let cell_ref = turbo_tasks().this_task_cell(T::get_value_type_id());
+    cell_ref.update(value);
+    cell_ref.into()
+}
@@ -0,0 +1,18 @@
+//! The task-execution loop's entry points into the rest of the crate.
+//!
+//! This file doesn't (yet, in this checkout) carry the full `TurboTasks`/`TurboTasksApi` engine —
+//! only the seam that every path re-executing a task's native function is expected to call through,
+//! so that task-scoped state (like the [`local_cell`][crate::local_cell] arena) is set up and torn
+//! down consistently no matter which of those paths runs.
+
+use crate::TaskId;
+
+/// Runs `execute` (a task's native function body) with task-local state scoped to `task_id`.
+///
+/// Every place that invokes a task's function — the normal scheduler loop, `run_once`,
+/// `dynamic_call`/`dynamic_this_call`, recursive execution of local tasks spawned from within
+/// another task — must call through here rather than invoking the function directly, or
+/// [`local_cell`][crate::local_cell] has no task to attribute its arena to.
+pub(crate) fn execute_task<R>(task_id: TaskId, execute: impl FnOnce() -> R) -> R {
+    crate::local_cell::with_local_cell_scope(task_id, execute)
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::{
+    local_cell::{resolve_local_cell, LocalCellId},
+    TaskId, VcValueType,
+};
+
+/// Identifies a single persistent cell within a task, numbered per value-type in the order the
+/// task's function created them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CellId {
+    pub index: u32,
+}
+
+/// A "raw", untyped handle to a [`Vc`][crate::Vc]'s backing cell.
+///
+/// Most `Vc`s point at a [`RawVc::TaskCell`]: a cell in the backend's persistent storage, keyed by
+/// `(TaskId, CellId)`, safe to hold onto (and serialize) past the end of the task execution that
+/// created it.
+///
+/// [`RawVc::LocalCell`] is the exception: it points into the *current* task's
+/// [local arena][crate::local_cell] instead, and is only valid for the duration of that task's
+/// execution. Every place that stores a `RawVc` outside of that scope (returning it from the task,
+/// writing it into a persistent cell, sending it across a `spawn`) must call [`RawVc::resolve`]
+/// first, which copies a `LocalCell` into a real `TaskCell` and leaves a `TaskCell` unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawVc {
+    TaskCell(TaskId, CellId),
+    LocalCell(TaskId, LocalCellId),
+}
+
+impl RawVc {
+    /// Returns a [`RawVc`] that's safe to use outside of the task that produced `self`.
+    ///
+    /// [`RawVc::TaskCell`] is already safe to use anywhere, so it's returned unchanged.
+    /// [`RawVc::LocalCell`] is promoted into a real persistent cell via
+    /// [`resolve_local_cell`][crate::local_cell::resolve_local_cell], which runs the normal
+    /// `UpdateCellOperation` path through the backend.
+    pub fn resolve<T: VcValueType + Clone>(self) -> RawVc {
+        match self {
+            RawVc::TaskCell(..) => self,
+            RawVc::LocalCell(task_id, id) => resolve_local_cell::<T>(task_id, id),
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, RawVc::LocalCell(..))
+    }
+}
+
+/// Returned when a trait `Vc` can't be cast down to the concrete type requested.
+#[derive(Debug)]
+pub struct ResolveTypeError;
+
+impl fmt::Display for ResolveTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to resolve trait object to the requested type")
+    }
+}
+
+impl std::error::Error for ResolveTypeError {}